@@ -0,0 +1,652 @@
+//! Subcommand dispatch. Each subcommand is a small `Cmd` implementor with
+//! its own `getopts::Options`, parsed from its argument slice and run
+//! against a live xfconf connection from a single dispatch table in
+//! `run()`. `completions` prints a shell script that calls back into the
+//! hidden `__list-monitors`/`__list-workspaces` subcommands so monitor
+//! names and workspace indices complete from live xfconf state.
+
+use std::env;
+
+use getopts::Options;
+
+use super::daemon;
+use super::geometry;
+use super::imageinfo::ImageSelector;
+use super::{XFCEDesktop, XFConfError, DEFAULT_ASPECT_TOLERANCE};
+
+const SUBCOMMANDS: &[&str] = &["query", "set-list", "set-img", "rotate", "set-mode", "daemon", "completions"];
+
+/// One subcommand: parsed from its own argument slice, run against a live
+/// xfconf connection.
+trait Cmd {
+    fn name(&self) -> &str;
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError>;
+}
+
+/// Cycle every monitor (and, outside single mode, every workspace)'s
+/// backdrop from the currently saved list file.
+fn rotate_now(xfconf: &XFCEDesktop, aspect_tolerance: f64) -> Result<(), XFConfError> {
+    let geometries = geometry::monitor_geometries().ok();
+    let mut selector = ImageSelector::new(aspect_tolerance);
+    xfconf.rotate_from_saved(geometries.as_ref(), &mut selector)
+}
+
+/// `query`: print the current list file, every (monitor, workspace)'s
+/// backdrop, and the single-workspace-mode setting.
+struct Query;
+
+impl Query {
+    fn parse(args: &[String]) -> Result<Query, String> {
+        let mut opts = Options::new();
+        opts.optflag("h", "help", "This help");
+
+        let matches = opts.parse(args).map_err(|e| e.to_string())?;
+        if matches.opt_present("h") {
+            return Err(opts.usage("Usage: query"));
+        }
+        Ok(Query)
+    }
+}
+
+impl Cmd for Query {
+    fn name(&self) -> &str { "query" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        println!("Current list file is : {}", xfconf.get_list()?);
+        println!("Current image file(s) set:");
+        for m in xfconf.monitors.clone().iter() {
+            println!(" {} : Mode = {}", m, if xfconf.single_mode { "single" } else { "seperate" });
+            for wsp_idx in 0..xfconf.workspace_count {
+                let wsp = format!("{}", wsp_idx);
+                println!("\tworkspace {}{}: {}", wsp_idx,
+                         if wsp_idx == xfconf.single_workspace { "*" } else { " " },
+                         xfconf.get_background(m, &wsp)?);
+            }
+        }
+
+        println!("Single backdrop mode = {}", xfconf.single_mode);
+        if xfconf.single_mode {
+            println!("Single backdrop mode workspace = {}", xfconf.single_workspace);
+        }
+        Ok(())
+    }
+}
+
+/// `set-list LISTFILE`: set the backdrop list file, optionally rotating
+/// immediately from it.
+struct SetList {
+    listfile: String,
+    rotate: bool,
+    aspect_tolerance: f64,
+}
+
+impl SetList {
+    fn parse(args: &[String]) -> Result<SetList, String> {
+        let mut opts = Options::new();
+        opts.optflag("c", "cycle", "Rotate backdrops from the new list immediately");
+        opts.optopt("", "aspect-tolerance", "Aspect ratio tolerance for resolution-aware image selection (default 0.1)", "TOL");
+        opts.optflag("h", "help", "This help");
+
+        let matches = opts.parse(args).map_err(|e| e.to_string())?;
+        if matches.opt_present("h") {
+            return Err(opts.usage("Usage: set-list [options] LISTFILE"));
+        }
+
+        let listfile = matches.free.get(0).cloned()
+            .ok_or_else(|| "set-list requires a LISTFILE argument".to_string())?;
+        let aspect_tolerance = matches.opt_str("aspect-tolerance")
+            .and_then(|t| t.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_ASPECT_TOLERANCE);
+
+        Ok(SetList { listfile: listfile, rotate: matches.opt_present("c"), aspect_tolerance: aspect_tolerance })
+    }
+}
+
+impl Cmd for SetList {
+    fn name(&self) -> &str { "set-list" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        println!("Current list file is : {}", xfconf.get_list()?);
+        println!("set-list(): Setting list = {}", self.listfile);
+        xfconf.set_list(&self.listfile)?;
+        if self.rotate {
+            rotate_now(xfconf, self.aspect_tolerance)?;
+        }
+        Ok(())
+    }
+}
+
+/// `set-img IMGFILE:IMGFILE:.. IMGFILE:..`: map ':'-separated image file
+/// names on to the (monitor, workspace) pairs inferred from the current
+/// xfce config, in sorted order. With `--repeat` the image list is
+/// cycled across the (monitor, workspace) pairs; otherwise any slots past
+/// the end of the list are left untouched.
+struct SetImg {
+    images: String,
+    repeat: bool,
+}
+
+impl SetImg {
+    fn parse(args: &[String]) -> Result<SetImg, String> {
+        let mut opts = Options::new();
+        opts.optflag("r", "repeat", "Repeat the image list when not enough images are given for every (monitor, workspace) slot");
+        opts.optflag("h", "help", "This help");
+
+        let matches = opts.parse(args).map_err(|e| e.to_string())?;
+        if matches.opt_present("h") {
+            return Err(opts.usage("Usage: set-img [options] IMGFILE:IMGFILE:.. IMGFILE:.."));
+        }
+        if matches.free.is_empty() {
+            return Err("set-img requires at least one IMGFILE argument".to_string());
+        }
+
+        Ok(SetImg { images: matches.free.join(":"), repeat: matches.opt_present("r") })
+    }
+}
+
+/// Zip `images` (a `:`-separated list) on to `slots` (the (monitor,
+/// workspace) pairs to fill, in order), repeating the image list if
+/// `repeat` and there are more slots than images. Blank entries from a
+/// doubled-up `:` in `images` are dropped rather than clearing a slot.
+fn pair_images<'a>(images: &'a str, repeat: bool, slots: Vec<(String, String)>) -> Vec<(&'a str, (String, String))> {
+    if repeat {
+        images.split(":").cycle().zip(slots).filter(|&(i, _)| !i.is_empty()).collect()
+    } else {
+        images.split(":").zip(slots).filter(|&(i, _)| !i.is_empty()).collect()
+    }
+}
+
+impl Cmd for SetImg {
+    fn name(&self) -> &str { "set-img" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        let workspaces = xfconf.workspace_names();
+        let all_workspaces = xfconf.monitors.iter()
+            .flat_map(|x| workspaces
+                      .iter()
+                      .map(|y| (x.to_string(), y.to_string()))
+                      .collect::<Vec<(String, String)>>())
+            .collect::<Vec<(String, String)>>();
+
+        let imgpairs = pair_images(&self.images, self.repeat, all_workspaces);
+
+        for &(i, (ref m, ref w)) in imgpairs.iter() {
+            println!("monitor{}, workspace-{}: {}", m, w, i);
+            if let Err(e) = xfconf.set_background(m, w, i) {
+                println!("Failed : {} ", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `rotate`: cycle backdrops from the currently saved list file.
+struct Rotate {
+    aspect_tolerance: f64,
+}
+
+impl Rotate {
+    fn parse(args: &[String]) -> Result<Rotate, String> {
+        let mut opts = Options::new();
+        opts.optopt("", "aspect-tolerance", "Aspect ratio tolerance for resolution-aware image selection (default 0.1)", "TOL");
+        opts.optflag("h", "help", "This help");
+
+        let matches = opts.parse(args).map_err(|e| e.to_string())?;
+        if matches.opt_present("h") {
+            return Err(opts.usage("Usage: rotate [options]"));
+        }
+
+        let aspect_tolerance = matches.opt_str("aspect-tolerance")
+            .and_then(|t| t.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_ASPECT_TOLERANCE);
+
+        Ok(Rotate { aspect_tolerance: aspect_tolerance })
+    }
+}
+
+impl Cmd for Rotate {
+    fn name(&self) -> &str { "rotate" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        rotate_now(xfconf, self.aspect_tolerance)
+    }
+}
+
+/// `set-mode (-s [WORKSPACE] | -m)`: switch between using a single
+/// backdrop across all workspaces or a separate one per workspace.
+struct SetMode {
+    mode: bool,
+    workspace: Option<i64>,
+    rotate: bool,
+    aspect_tolerance: f64,
+}
+
+impl SetMode {
+    fn parse(args: &[String]) -> Result<SetMode, String> {
+        let mut opts = Options::new();
+        opts.optflagopt("s", "single", "Use backdrop from specified workspace for others.", "WORKSPACE");
+        opts.optflag("m", "multiple", "Turn off using single backdrop across all workspaces. Dont use together with '-s'");
+        opts.optflag("c", "cycle", "Rotate backdrops immediately after changing mode");
+        opts.optopt("", "aspect-tolerance", "Aspect ratio tolerance for resolution-aware image selection (default 0.1)", "TOL");
+        opts.optflag("h", "help", "This help");
+
+        let matches = opts.parse(args).map_err(|e| e.to_string())?;
+        if matches.opt_present("h") {
+            return Err(opts.usage("Usage: set-mode (-s [WORKSPACE] | -m) [options]"));
+        }
+        if matches.opt_present("s") == matches.opt_present("m") {
+            return Err("set-mode requires exactly one of -s or -m".to_string());
+        }
+
+        let aspect_tolerance = matches.opt_str("aspect-tolerance")
+            .and_then(|t| t.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_ASPECT_TOLERANCE);
+        let rotate = matches.opt_present("c");
+
+        if matches.opt_present("s") {
+            let workspace = match matches.opt_str("s") {
+                Some(w) => Some(w.parse::<i64>().map_err(|_| format!("Bad workspace index specified : '{}'", w))?),
+                None => None,
+            };
+            Ok(SetMode { mode: true, workspace: workspace, rotate: rotate, aspect_tolerance: aspect_tolerance })
+        } else {
+            Ok(SetMode { mode: false, workspace: None, rotate: rotate, aspect_tolerance: aspect_tolerance })
+        }
+    }
+}
+
+impl Cmd for SetMode {
+    fn name(&self) -> &str { "set-mode" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        let workspace = match self.workspace {
+            Some(n) if (n < 0) || (n >= (xfconf.workspace_count as i64)) => {
+                println!("Workspace index ({}) outside valid range [{}..{}]. Not changing it.", n, 0, xfconf.workspace_count);
+                None
+            }
+            other => other,
+        };
+
+        xfconf.set_single_workspace_info(self.mode, workspace)?;
+        if self.rotate {
+            xfconf.refresh_single_workspace_info()?;
+            rotate_now(xfconf, self.aspect_tolerance)?;
+        }
+        Ok(())
+    }
+}
+
+/// `daemon`: stay resident and rotate each monitor's backdrop on its own
+/// timer instead of requiring the binary to be re-invoked for every cycle.
+struct Daemon {
+    opts: daemon::DaemonOpts,
+}
+
+impl Daemon {
+    fn parse(args: &[String]) -> Result<Daemon, String> {
+        let mut opts = Options::new();
+        opts.optmulti("", "interval", "Daemon rotation interval in seconds, or NAME=SECS[,NAME=SECS,..] per monitor (repeatable)", "SPEC");
+        opts.optopt("", "batch-slice", "How close together (ms) two monitors' fire times need to be to rotate in one pass (default 500)", "MS");
+        opts.optopt("", "aspect-tolerance", "Aspect ratio tolerance for resolution-aware image selection (default 0.1)", "TOL");
+        opts.optflag("h", "help", "This help");
+
+        let matches = opts.parse(args).map_err(|e| e.to_string())?;
+        if matches.opt_present("h") {
+            return Err(opts.usage("Usage: daemon [options]"));
+        }
+
+        let mut daemon_opts = daemon::parse_interval_opts(&matches.opt_strs("interval"), daemon::DEFAULT_INTERVAL_SECS);
+        if let Some(ms) = matches.opt_str("batch-slice") {
+            daemon_opts.batch_slice_ms = ms.parse::<u64>().map_err(|_| format!("Bad batch-slice value : '{}'", ms))?;
+        }
+        daemon_opts.aspect_tolerance = matches.opt_str("aspect-tolerance")
+            .and_then(|t| t.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_ASPECT_TOLERANCE);
+        Ok(Daemon { opts: daemon_opts })
+    }
+}
+
+impl Cmd for Daemon {
+    fn name(&self) -> &str { "daemon" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        daemon::run(xfconf, self.opts.clone())
+    }
+}
+
+/// Hidden: print each monitor name, one per line. Used by the shell
+/// completion scripts generated by `completions`, not meant to be run
+/// interactively.
+struct ListMonitors;
+
+impl Cmd for ListMonitors {
+    fn name(&self) -> &str { "__list-monitors" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        for m in xfconf.monitors.iter() {
+            println!("{}", m);
+        }
+        Ok(())
+    }
+}
+
+/// Hidden: print each workspace index, one per line. Used by the shell
+/// completion scripts generated by `completions`.
+struct ListWorkspaces;
+
+impl Cmd for ListWorkspaces {
+    fn name(&self) -> &str { "__list-workspaces" }
+
+    fn run(&self, xfconf: &mut XFCEDesktop) -> Result<(), XFConfError> {
+        for w in xfconf.workspace_names().iter() {
+            println!("{}", w);
+        }
+        Ok(())
+    }
+}
+
+fn build(sub: &str, rest: &[String]) -> Result<Box<Cmd>, String> {
+    match sub {
+        "query" => Query::parse(rest).map(|c| Box::new(c) as Box<Cmd>),
+        "set-list" => SetList::parse(rest).map(|c| Box::new(c) as Box<Cmd>),
+        "set-img" => SetImg::parse(rest).map(|c| Box::new(c) as Box<Cmd>),
+        "rotate" => Rotate::parse(rest).map(|c| Box::new(c) as Box<Cmd>),
+        "set-mode" => SetMode::parse(rest).map(|c| Box::new(c) as Box<Cmd>),
+        "daemon" => Daemon::parse(rest).map(|c| Box::new(c) as Box<Cmd>),
+        "__list-monitors" => Ok(Box::new(ListMonitors) as Box<Cmd>),
+        "__list-workspaces" => Ok(Box::new(ListWorkspaces) as Box<Cmd>),
+        other => Err(format!("Unknown subcommand '{}'. Run '{}' with no arguments for usage.", other, "xfcesetbg")),
+    }
+}
+
+fn print_usage(progname: &str) {
+    println!("Usage: {} SUBCOMMAND [options]", progname);
+    println!("");
+    println!("Subcommands:");
+    println!("  query                Show the current list file and backdrop settings");
+    println!("  set-list LISTFILE    Set the backdrop list file");
+    println!("  set-img IMG:IMG:..   Set specific backdrop image(s) directly");
+    println!("  rotate               Cycle backdrops from the current list file");
+    println!("  set-mode (-s|-m)     Switch single/multi workspace backdrop mode");
+    println!("  daemon               Stay resident and rotate backdrops on a per-monitor timer");
+    println!("  completions SHELL    Print a completion script for bash, zsh or fish");
+    println!("");
+    println!("Run 'SUBCOMMAND -h' for subcommand-specific options.");
+}
+
+/// The flag spellings (short and long) registered by each subcommand's
+/// `parse` (e.g. `SetList::parse`). Kept next to this match so a flag added to a `parse`'s
+/// `getopts::Options` is easy to mirror here; this is what the generated
+/// completion scripts offer once the subcommand name is typed.
+fn subcommand_options(sub: &str) -> &'static [&'static str] {
+    match sub {
+        "query" => &["-h", "--help"],
+        "set-list" => &["-c", "--cycle", "--aspect-tolerance", "-h", "--help"],
+        "set-img" => &["-r", "--repeat", "-h", "--help"],
+        "rotate" => &["--aspect-tolerance", "-h", "--help"],
+        "set-mode" => &["-s", "--single", "-m", "--multiple", "-c", "--cycle", "--aspect-tolerance", "-h", "--help"],
+        "daemon" => &["--interval", "--batch-slice", "--aspect-tolerance", "-h", "--help"],
+        "completions" => &["-h", "--help"],
+        _ => &[],
+    }
+}
+
+/// bash completion script: completes the subcommand name, that
+/// subcommand's own options (from `subcommand_options`), and dynamic
+/// monitor names / workspace indices for the flags that take them by
+/// shelling back out to the hidden `__list-*` subcommands.
+fn bash_completions(progname: &str) -> String {
+    let opt_cases: String = SUBCOMMANDS.iter()
+        .map(|s| format!("            {})\n                opts=\"{}\"\n                ;;\n", s, subcommand_options(s).join(" ")))
+        .collect::<Vec<String>>()
+        .join("");
+
+    format!(r#"# bash completion for {prog}
+_{prog}_complete() {{
+    local cur prev subcmd opts
+    COMPREPLY=()
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    subcmd="${{COMP_WORDS[1]}}"
+
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=( $(compgen -W "{subs}" -- "$cur") )
+        return 0
+    fi
+
+    case "$prev" in
+        -s|--single)
+            COMPREPLY=( $(compgen -W "$({prog} __list-workspaces 2>/dev/null)" -- "$cur") )
+            return 0
+            ;;
+        --interval)
+            COMPREPLY=( $(compgen -W "$({prog} __list-monitors 2>/dev/null)" -- "$cur") )
+            return 0
+            ;;
+    esac
+
+    if [ "$subcmd" = "completions" ]; then
+        COMPREPLY=( $(compgen -W "bash zsh fish" -- "$cur") )
+        return 0
+    fi
+
+    if [[ "$cur" == -* ]]; then
+        opts=""
+        case "$subcmd" in
+{opt_cases}        esac
+        COMPREPLY=( $(compgen -W "$opts" -- "$cur") )
+        return 0
+    fi
+
+    COMPREPLY=( $(compgen -f -- "$cur") )
+}}
+complete -F _{prog}_complete {prog}
+"#, prog = progname, subs = SUBCOMMANDS.join(" "), opt_cases = opt_cases)
+}
+
+/// zsh completion script: same dynamic monitor/workspace completion and
+/// per-subcommand option lists as the bash script, expressed via `_describe`.
+fn zsh_completions(progname: &str) -> String {
+    let opt_cases: String = SUBCOMMANDS.iter()
+        .map(|s| format!("            {})\n                options=({})\n                ;;\n", s, subcommand_options(s).join(" ")))
+        .collect::<Vec<String>>()
+        .join("");
+
+    format!(r#"#compdef {prog}
+
+_{prog}() {{
+    local -a subcommands options
+    subcommands=({subs})
+
+    if (( CURRENT == 2 )); then
+        _describe 'command' subcommands
+        return
+    fi
+
+    case "$words[2]" in
+        set-mode)
+            if [[ "$words[CURRENT-1]" == "-s" || "$words[CURRENT-1]" == "--single" ]]; then
+                local -a workspaces
+                workspaces=(${{(f)"$({prog} __list-workspaces 2>/dev/null)"}})
+                _describe 'workspace' workspaces
+                return
+            fi
+            ;;
+        daemon)
+            if [[ "$words[CURRENT-1]" == "--interval" ]]; then
+                local -a monitors
+                monitors=(${{(f)"$({prog} __list-monitors 2>/dev/null)"}})
+                _describe 'monitor' monitors
+                return
+            fi
+            ;;
+        completions)
+            local -a shells
+            shells=(bash zsh fish)
+            _describe 'shell' shells
+            return
+            ;;
+    esac
+
+    if [[ "$words[CURRENT]" == -* ]]; then
+        case "$words[2]" in
+{opt_cases}        esac
+        _describe 'option' options
+        return
+    fi
+
+    _files
+}}
+
+_{prog} "$@"
+"#, prog = progname, subs = SUBCOMMANDS.join(" "), opt_cases = opt_cases)
+}
+
+/// fish completion script: same dynamic monitor/workspace completion,
+/// plus one `complete` line per subcommand option (from
+/// `subcommand_options`), each emitted as either `-l LONG` or `-s SHORT`
+/// and scoped to that subcommand via `__fish_seen_subcommand_from`.
+fn fish_completions(progname: &str) -> String {
+    let mut opt_lines = String::new();
+    for sub in SUBCOMMANDS {
+        for opt in subcommand_options(sub) {
+            if let Some(long) = opt.strip_prefix("--") {
+                opt_lines.push_str(&format!(
+                    "complete -c {prog} -n '__fish_seen_subcommand_from {sub}' -l {long}\n",
+                    prog = progname, sub = sub, long = long));
+            } else if let Some(short) = opt.strip_prefix("-") {
+                opt_lines.push_str(&format!(
+                    "complete -c {prog} -n '__fish_seen_subcommand_from {sub}' -s {short}\n",
+                    prog = progname, sub = sub, short = short));
+            }
+        }
+    }
+
+    format!(r#"# fish completion for {prog}
+complete -c {prog} -f
+complete -c {prog} -n '__fish_use_subcommand' -a '{subs}'
+complete -c {prog} -n '__fish_seen_subcommand_from set-mode' -s s -l single -a '({prog} __list-workspaces)'
+complete -c {prog} -n '__fish_seen_subcommand_from daemon' -l interval -a '({prog} __list-monitors)'
+complete -c {prog} -n '__fish_seen_subcommand_from completions' -a 'bash zsh fish'
+{opt_lines}"#, prog = progname, subs = SUBCOMMANDS.join(" "), opt_lines = opt_lines)
+}
+
+pub fn run() {
+    let args: Vec<String> = env::args().collect();
+    let progname = args[0].clone();
+
+    if args.len() < 2 {
+        print_usage(&progname);
+        return;
+    }
+
+    let sub = args[1].as_str();
+    let rest = &args[2..];
+
+    if sub == "-h" || sub == "--help" || sub == "help" {
+        print_usage(&progname);
+        return;
+    }
+
+    if sub == "completions" {
+        match rest.get(0).map(String::as_str) {
+            Some("bash") => print!("{}", bash_completions(&progname)),
+            Some("zsh") => print!("{}", zsh_completions(&progname)),
+            Some("fish") => print!("{}", fish_completions(&progname)),
+            _ => println!("Usage: {} completions <bash|zsh|fish>", progname),
+        }
+        return;
+    }
+
+    let cmd = match build(sub, rest) {
+        Ok(c) => c,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    let mut xfconf = match XFCEDesktop::new() {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Could not connect to xfconf: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = cmd.run(&mut xfconf) {
+        println!("{} failed: {}", cmd.name(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(words: &[&str]) -> Vec<String> {
+        words.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn set_mode_requires_exactly_one_of_single_or_multiple() {
+        assert!(SetMode::parse(&args(&[])).is_err());
+        assert!(SetMode::parse(&args(&["-s", "-m"])).is_err());
+    }
+
+    #[test]
+    fn set_mode_single_accepts_an_optional_workspace() {
+        let mode = SetMode::parse(&args(&["-s", "2"])).unwrap();
+        assert_eq!(mode.mode, true);
+        assert_eq!(mode.workspace, Some(2));
+    }
+
+    #[test]
+    fn set_mode_single_without_workspace_leaves_it_unset() {
+        let mode = SetMode::parse(&args(&["-s"])).unwrap();
+        assert_eq!(mode.mode, true);
+        assert_eq!(mode.workspace, None);
+    }
+
+    #[test]
+    fn set_mode_multiple_sets_mode_false() {
+        let mode = SetMode::parse(&args(&["-m"])).unwrap();
+        assert_eq!(mode.mode, false);
+        assert_eq!(mode.workspace, None);
+    }
+
+    #[test]
+    fn set_mode_rejects_a_non_numeric_workspace() {
+        assert!(SetMode::parse(&args(&["-s", "not-a-number"])).is_err());
+    }
+
+    fn slots(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|&(m, w)| (m.to_string(), w.to_string())).collect()
+    }
+
+    #[test]
+    fn pair_images_zips_one_image_per_slot() {
+        let pairs = pair_images("a.jpg:b.jpg", false, slots(&[("DP1", "0"), ("HDMI1", "0")]));
+        assert_eq!(pairs, vec![("a.jpg", ("DP1".to_string(), "0".to_string())),
+                                ("b.jpg", ("HDMI1".to_string(), "0".to_string()))]);
+    }
+
+    #[test]
+    fn pair_images_leaves_extra_slots_unfilled_without_repeat() {
+        let pairs = pair_images("a.jpg", false, slots(&[("DP1", "0"), ("HDMI1", "0")]));
+        assert_eq!(pairs, vec![("a.jpg", ("DP1".to_string(), "0".to_string()))]);
+    }
+
+    #[test]
+    fn pair_images_cycles_the_image_list_with_repeat() {
+        let pairs = pair_images("a.jpg", true, slots(&[("DP1", "0"), ("HDMI1", "0")]));
+        assert_eq!(pairs, vec![("a.jpg", ("DP1".to_string(), "0".to_string())),
+                                ("a.jpg", ("HDMI1".to_string(), "0".to_string()))]);
+    }
+
+    #[test]
+    fn pair_images_drops_empty_entries_from_doubled_separators() {
+        let pairs = pair_images("a.jpg::b.jpg", false, slots(&[("DP1", "0"), ("HDMI1", "0"), ("VGA1", "0")]));
+        assert_eq!(pairs, vec![("a.jpg", ("DP1".to_string(), "0".to_string())),
+                                ("b.jpg", ("VGA1".to_string(), "0".to_string()))]);
+    }
+}
@@ -0,0 +1,109 @@
+//! Control-socket listener for daemon mode.
+//!
+//! Lets an external command force an immediate rotation, reload the
+//! monitor/workspace layout, or change a rotation interval without having
+//! to send the daemon a signal.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+/// A request received over the control socket.
+#[derive(Debug, Clone)]
+pub enum ControlMsg {
+    /// Force every monitor to rotate now.
+    RotateNow,
+    /// Re-read the monitor/workspace layout.
+    Reload,
+    /// Change the rotation interval for one monitor, or for every monitor
+    /// without its own override if `monitor` is `None`.
+    SetInterval { monitor: Option<String>, seconds: u64 },
+}
+
+/// Default control socket path: `$XDG_RUNTIME_DIR/xfcesetbg.sock`, falling
+/// back to `/tmp/xfcesetbg-<user>.sock`.
+pub fn default_socket_path() -> PathBuf {
+    if let Ok(dir) = std::env::var("XDG_RUNTIME_DIR") {
+        return Path::new(&dir).join("xfcesetbg.sock");
+    }
+    let user = std::env::var("USER").unwrap_or_else(|_| "unknown".to_string());
+    PathBuf::from(format!("/tmp/xfcesetbg-{}.sock", user))
+}
+
+/// Bind `path` and spawn a thread that accepts connections, forwarding
+/// parsed commands to `tx`. Stale sockets left behind by a previous run
+/// are removed before binding.
+pub fn spawn_listener(path: PathBuf, tx: Sender<ControlMsg>) -> std::io::Result<PathBuf> {
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    let bound_path = path.clone();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Ok(stream) = stream {
+                let tx = tx.clone();
+                thread::spawn(move || handle_client(stream, tx));
+            }
+        }
+    });
+
+    Ok(bound_path)
+}
+
+fn handle_client(stream: UnixStream, tx: Sender<ControlMsg>) {
+    let mut reply = stream.try_clone().ok();
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+
+        match parse_command(&line) {
+            Some(msg) => {
+                let _ = tx.send(msg);
+                if let Some(ref mut w) = reply {
+                    let _ = writeln!(w, "ok");
+                }
+            }
+            None => {
+                if let Some(ref mut w) = reply {
+                    let _ = writeln!(w, "error: unrecognised command");
+                }
+            }
+        }
+    }
+}
+
+/// Parse one line of control-socket input into a `ControlMsg`.
+///
+/// Recognised commands: `rotate-now`, `reload`, `set-interval SECONDS`
+/// and `set-interval MONITOR=SECONDS`.
+fn parse_command(line: &str) -> Option<ControlMsg> {
+    let line = line.trim();
+
+    if line == "rotate-now" {
+        return Some(ControlMsg::RotateNow);
+    }
+    if line == "reload" {
+        return Some(ControlMsg::Reload);
+    }
+    if line.starts_with("set-interval ") {
+        let rest = &line["set-interval ".len()..];
+        let mut kv = rest.splitn(2, '=');
+        return match (kv.next(), kv.next()) {
+            (Some(mon), Some(secs)) => secs.trim().parse::<u64>().ok().map(|s| {
+                ControlMsg::SetInterval { monitor: Some(mon.trim().to_string()), seconds: s }
+            }),
+            (Some(secs_only), None) => secs_only.trim().parse::<u64>().ok().map(|s| {
+                ControlMsg::SetInterval { monitor: None, seconds: s }
+            }),
+            _ => None,
+        };
+    }
+
+    None
+}
@@ -0,0 +1,258 @@
+//! Daemon mode: keeps the xfconf connection alive and rotates each
+//! monitor's backdrop on its own timer instead of requiring the binary to
+//! be re-invoked (e.g. from cron) for every cycle.
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use super::control::{self, ControlMsg};
+use super::geometry::{self, GeometryMap};
+use super::imageinfo::ImageSelector;
+use super::scheduler::RotationSchedule;
+use super::{XFCEDesktop, XFConfError, DEFAULT_ASPECT_TOLERANCE};
+
+/// Default for how close together two monitors' fire times need to be
+/// before the daemon treats them as the same wake-up and rotates both in
+/// one pass. Overridable via `--batch-slice`.
+pub const DEFAULT_BATCH_SLICE_MS: u64 = 500;
+
+/// How long to sleep when the schedule is (momentarily) empty.
+const IDLE_WAIT: Duration = Duration::from_secs(3600);
+
+/// Default rotation interval used when `--interval` gives no bare number.
+pub const DEFAULT_INTERVAL_SECS: u64 = 600;
+
+/// Per-monitor rotation intervals, parsed from `--interval`, plus the
+/// batching window for near-simultaneous fire times and the aspect-ratio
+/// tolerance used for resolution-aware image selection.
+#[derive(Clone)]
+pub struct DaemonOpts {
+    pub default_interval_secs: u64,
+    pub per_monitor_secs: HashMap<String, u64>,
+    pub batch_slice_ms: u64,
+    pub aspect_tolerance: f64,
+}
+
+/// Parse the (possibly repeated) `--interval` values into a default
+/// interval plus per-monitor overrides.
+///
+/// Each value is either a bare number of seconds (`600`), which replaces
+/// the default, or a comma-separated `MONITOR=SECONDS` list
+/// (`DP1=600,HDMI1=300`).
+pub fn parse_interval_opts(values: &[String], default_interval_secs: u64) -> DaemonOpts {
+    let mut opts = DaemonOpts {
+        default_interval_secs: default_interval_secs,
+        per_monitor_secs: HashMap::new(),
+        batch_slice_ms: DEFAULT_BATCH_SLICE_MS,
+        aspect_tolerance: DEFAULT_ASPECT_TOLERANCE,
+    };
+
+    for value in values {
+        if !value.contains('=') {
+            if let Ok(secs) = value.trim().parse::<u64>() {
+                opts.default_interval_secs = secs;
+            }
+            continue;
+        }
+
+        for part in value.split(',') {
+            let mut kv = part.splitn(2, '=');
+            if let (Some(mon), Some(secs)) = (kv.next(), kv.next()) {
+                if let Ok(secs) = secs.trim().parse::<u64>() {
+                    opts.per_monitor_secs.insert(mon.trim().to_string(), secs);
+                }
+            }
+        }
+    }
+
+    opts
+}
+
+fn interval_for(opts: &DaemonOpts, monitor: &str) -> Duration {
+    let secs = *opts.per_monitor_secs.get(monitor).unwrap_or(&opts.default_interval_secs);
+    Duration::from_secs(secs)
+}
+
+/// Add newly hot-plugged monitors to the schedule and drop ones that
+/// disappeared, diffing against the live xfconf monitor list.
+fn sync_monitors(xfconf: &XFCEDesktop, opts: &DaemonOpts, schedule: &mut RotationSchedule, now: Instant) {
+    for monitor in &xfconf.monitors {
+        if !schedule.contains(monitor) {
+            schedule.push(monitor, now, interval_for(opts, monitor));
+        }
+    }
+
+    let live = xfconf.monitors.clone();
+    schedule.retain(|m| live.iter().any(|x| x == m));
+}
+
+/// Rotate every workspace slot `monitor` currently owns, respecting
+/// single/multi workspace mode. When `geometries` has an entry for
+/// `monitor`, the pick is filtered through `selector` against it first.
+fn rotate_monitor(xfconf: &XFCEDesktop, monitor: &str, image_names: &Vec<String>,
+                   geometries: Option<&GeometryMap>, selector: &mut ImageSelector) -> Result<(), XFConfError> {
+    use super::imageinfo::PickTarget;
+
+    let mut target = geometries.and_then(|g| g.get(monitor)).map(|dims| PickTarget { selector: &mut *selector, geometry: *dims });
+
+    if xfconf.single_mode {
+        let wsp = format!("{}", xfconf.single_workspace);
+        xfconf.rotate_background_for_monitor(monitor, &wsp, image_names, target.as_mut())?;
+    } else {
+        for wsp in xfconf.workspace_names().iter() {
+            xfconf.rotate_background_for_monitor(monitor, wsp, image_names, target.as_mut())?;
+        }
+    }
+    Ok(())
+}
+
+/// Rotate every monitor immediately (used by the `rotate-now` control
+/// command) and reschedule each one's timer from this instant.
+fn rotate_now_all(xfconf: &XFCEDesktop, schedule: &mut RotationSchedule, opts: &DaemonOpts,
+                   geometries: Option<&GeometryMap>, selector: &mut ImageSelector) -> Result<(), XFConfError> {
+    let image_names = xfconf.get_image_names(xfconf.get_list()?.as_str())?;
+    let now = Instant::now();
+
+    for monitor in xfconf.monitors.clone() {
+        rotate_monitor(xfconf, &monitor, &image_names, geometries, selector)?;
+        schedule.reschedule(&monitor, now, interval_for(opts, &monitor));
+    }
+    Ok(())
+}
+
+/// Run the daemon loop: maintain a `RotationSchedule` keyed by each
+/// monitor's next-fire instant, sleep until the soonest one is due (or
+/// until a control-socket command wakes the loop early), rotate the
+/// monitors that fired, and repeat. On every tick the monitor/workspace
+/// layout is refreshed so hot-plugging or switching single/multi mode is
+/// picked up without restarting the daemon.
+pub fn run(xfconf: &mut XFCEDesktop, mut opts: DaemonOpts) -> Result<(), XFConfError> {
+    let batch_slice = Duration::from_millis(opts.batch_slice_ms);
+    let start = Instant::now();
+    let mut schedule = RotationSchedule::new();
+    for monitor in xfconf.monitors.clone().iter() {
+        schedule.push(monitor, start, interval_for(&opts, monitor));
+    }
+
+    let (tx, rx) = mpsc::channel::<ControlMsg>();
+    let socket_path = control::default_socket_path();
+    match control::spawn_listener(socket_path.clone(), tx) {
+        Ok(p) => println!("daemon: control socket listening at {}", p.display()),
+        Err(e) => println!("daemon: could not open control socket at {}: {}", socket_path.display(), e),
+    }
+
+    let mut geometries = geometry::monitor_geometries().ok();
+    let mut selector = ImageSelector::new(opts.aspect_tolerance);
+
+    loop {
+        let now = Instant::now();
+        let wait = match schedule.next_wake() {
+            Some(at) if at > now => at - now,
+            Some(_) => Duration::from_secs(0),
+            None => IDLE_WAIT,
+        };
+
+        match rx.recv_timeout(wait) {
+            Ok(ControlMsg::RotateNow) => {
+                if let Err(e) = rotate_now_all(xfconf, &mut schedule, &opts, geometries.as_ref(), &mut selector) {
+                    println!("daemon: rotate-now failed: {}", e);
+                }
+            }
+            Ok(ControlMsg::Reload) => {
+                if let Err(e) = xfconf.refresh_monitors_and_workspaces() {
+                    println!("daemon: reload failed: {}", e);
+                } else {
+                    let _ = xfconf.refresh_single_workspace_info();
+                    sync_monitors(xfconf, &opts, &mut schedule, Instant::now());
+                    geometries = geometry::monitor_geometries().ok();
+                    println!("daemon: reloaded monitor/workspace layout");
+                }
+            }
+            Ok(ControlMsg::SetInterval { monitor: Some(m), seconds }) => {
+                opts.per_monitor_secs.insert(m.clone(), seconds);
+                schedule.reschedule(&m, Instant::now(), Duration::from_secs(seconds));
+                println!("daemon: interval for {} set to {}s", m, seconds);
+            }
+            Ok(ControlMsg::SetInterval { monitor: None, seconds }) => {
+                opts.default_interval_secs = seconds;
+                let now = Instant::now();
+                for m in xfconf.monitors.clone() {
+                    if !opts.per_monitor_secs.contains_key(&m) {
+                        schedule.reschedule(&m, now, Duration::from_secs(seconds));
+                    }
+                }
+                println!("daemon: default interval set to {}s", seconds);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                if let Err(e) = xfconf.refresh_monitors_and_workspaces() {
+                    println!("daemon: could not refresh monitors: {}", e);
+                } else {
+                    let _ = xfconf.refresh_single_workspace_info();
+                    sync_monitors(xfconf, &opts, &mut schedule, now);
+                    geometries = geometry::monitor_geometries().ok();
+                }
+
+                let due = schedule.pop_ready(now, batch_slice);
+                if !due.is_empty() {
+                    match xfconf.get_list().and_then(|l| xfconf.get_image_names(l.as_str())) {
+                        Ok(image_names) => {
+                            for monitor in &due {
+                                if let Err(e) = rotate_monitor(xfconf, monitor, &image_names, geometries.as_ref(), &mut selector) {
+                                    println!("daemon: rotation failed for {}: {}", monitor, e);
+                                }
+                            }
+                        }
+                        Err(e) => println!("daemon: could not read image list: {}", e),
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_number_overrides_the_default_interval() {
+        let opts = parse_interval_opts(&["900".to_string()], DEFAULT_INTERVAL_SECS);
+        assert_eq!(opts.default_interval_secs, 900);
+        assert!(opts.per_monitor_secs.is_empty());
+    }
+
+    #[test]
+    fn name_equals_secs_sets_a_per_monitor_override() {
+        let opts = parse_interval_opts(&["DP1=300".to_string()], DEFAULT_INTERVAL_SECS);
+        assert_eq!(opts.default_interval_secs, DEFAULT_INTERVAL_SECS);
+        assert_eq!(opts.per_monitor_secs.get("DP1"), Some(&300));
+    }
+
+    #[test]
+    fn comma_separated_list_sets_multiple_monitors() {
+        let opts = parse_interval_opts(&["DP1=600,HDMI1=300".to_string()], DEFAULT_INTERVAL_SECS);
+        assert_eq!(opts.per_monitor_secs.get("DP1"), Some(&600));
+        assert_eq!(opts.per_monitor_secs.get("HDMI1"), Some(&300));
+    }
+
+    #[test]
+    fn malformed_entries_are_silently_ignored() {
+        let opts = parse_interval_opts(&["DP1=notanumber".to_string(), "HDMI1=".to_string()], DEFAULT_INTERVAL_SECS);
+        assert!(opts.per_monitor_secs.is_empty());
+        assert_eq!(opts.default_interval_secs, DEFAULT_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn later_values_override_earlier_ones() {
+        let opts = parse_interval_opts(
+            &["600".to_string(), "900".to_string(), "DP1=100".to_string(), "DP1=200".to_string()],
+            DEFAULT_INTERVAL_SECS);
+        assert_eq!(opts.default_interval_secs, 900);
+        assert_eq!(opts.per_monitor_secs.get("DP1"), Some(&200));
+    }
+}
@@ -0,0 +1,63 @@
+//! Monitor pixel geometry via XRandR, keyed by the same monitor names
+//! `XFCEDesktop::refresh_monitors_and_workspaces` scrapes out of xfconf
+//! property paths (e.g. `"DP1"`, `"HDMI1"`).
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::ptr;
+
+use x11::xlib;
+use x11::xrandr;
+
+use super::XFConfError;
+
+/// Width/height in pixels of each connected output, keyed by output name.
+pub type GeometryMap = HashMap<String, (u32, u32)>;
+
+/// Query XRandR for the current pixel width/height of every connected,
+/// active output.
+pub fn monitor_geometries() -> Result<GeometryMap, XFConfError> {
+    let mut geometries = GeometryMap::new();
+
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return Err(XFConfError::GeometryError("could not open X display".to_string()));
+        }
+
+        let root = xlib::XDefaultRootWindow(display);
+        let resources = xrandr::XRRGetScreenResourcesCurrent(display, root);
+        if resources.is_null() {
+            xlib::XCloseDisplay(display);
+            return Err(XFConfError::GeometryError("XRandR returned no screen resources".to_string()));
+        }
+
+        let res = &*resources;
+        for i in 0..res.noutput {
+            let output = *res.outputs.offset(i as isize);
+            let info = xrandr::XRRGetOutputInfo(display, resources, output);
+            if info.is_null() {
+                continue;
+            }
+            let output_info = &*info;
+
+            if output_info.connection == 0 && output_info.crtc != 0 {
+                // connection == RR_Connected
+                let name = CStr::from_ptr(output_info.name).to_string_lossy().into_owned();
+                let crtc_info = xrandr::XRRGetCrtcInfo(display, resources, output_info.crtc);
+                if !crtc_info.is_null() {
+                    let crtc = &*crtc_info;
+                    geometries.insert(name, (crtc.width as u32, crtc.height as u32));
+                    xrandr::XRRFreeCrtcInfo(crtc_info);
+                }
+            }
+
+            xrandr::XRRFreeOutputInfo(info);
+        }
+
+        xrandr::XRRFreeScreenResources(resources);
+        xlib::XCloseDisplay(display);
+    }
+
+    Ok(geometries)
+}
@@ -0,0 +1,134 @@
+//! Cheap, header-only image dimension probing, used to match candidate
+//! backdrops against monitor geometry without decoding whole images.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use image;
+
+use super::XFConfError;
+
+/// Caches probed `(width, height)` pairs across a single rotation pass so
+/// a list of images is not re-opened once per monitor.
+pub struct ImageSelector {
+    /// Fraction of deviation from a monitor's aspect ratio that is still
+    /// considered a match, e.g. `0.1` allows +/-10%.
+    tolerance: f64,
+    cache: HashMap<String, (u32, u32)>,
+}
+
+impl ImageSelector {
+    pub fn new(tolerance: f64) -> ImageSelector {
+        ImageSelector { tolerance: tolerance, cache: HashMap::new() }
+    }
+
+    /// Probe (and cache) the pixel dimensions of `path`, reading only
+    /// enough of the file to decode its header.
+    fn dimensions(&mut self, path: &str) -> Result<(u32, u32), XFConfError> {
+        if let Some(dims) = self.cache.get(path) {
+            return Ok(*dims);
+        }
+
+        let dims = image::image_dimensions(Path::new(path))
+            .map_err(|e| XFConfError::ImageProbeError(format!("{}: {}", path, e)))?;
+        self.cache.insert(path.to_string(), dims);
+        Ok(dims)
+    }
+
+    /// Filter `image_names` down to those at least as large as `target`
+    /// and whose aspect ratio is within tolerance of it. Images whose
+    /// dimensions cannot be probed are dropped rather than failing the
+    /// whole selection.
+    pub fn filter_candidates(&mut self, image_names: &Vec<String>, target: (u32, u32)) -> Vec<String> {
+        let (target_w, target_h) = target;
+        if target_w == 0 || target_h == 0 {
+            return image_names.clone();
+        }
+        let target_ratio = target_w as f64 / target_h as f64;
+
+        image_names.iter()
+            .filter(|name| {
+                match self.dimensions(name) {
+                    Ok((w, h)) if w >= target_w && h >= target_h => {
+                        let ratio = w as f64 / h as f64;
+                        ((ratio - target_ratio).abs() / target_ratio) <= self.tolerance
+                    }
+                    _ => false,
+                }
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// A monitor's geometry plus the selector used to filter against it,
+/// threaded through `XFCEDesktop::pick_image` so a candidate pool can be
+/// narrowed to appropriately-sized, correctly-proportioned images.
+pub struct PickTarget<'a> {
+    pub selector: &'a mut ImageSelector,
+    pub geometry: (u32, u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a selector whose cache is pre-populated, so `filter_candidates`
+    /// can be exercised without touching any real image files on disk.
+    fn selector_with(tolerance: f64, entries: &[(&str, (u32, u32))]) -> ImageSelector {
+        let mut cache = HashMap::new();
+        for &(name, dims) in entries {
+            cache.insert(name.to_string(), dims);
+        }
+        ImageSelector { tolerance: tolerance, cache: cache }
+    }
+
+    #[test]
+    fn keeps_images_within_tolerance() {
+        let mut selector = selector_with(0.1, &[("a.jpg", (1920, 1080))]);
+        let names = vec!["a.jpg".to_string()];
+
+        assert_eq!(selector.filter_candidates(&names, (1920, 1080)), names);
+    }
+
+    #[test]
+    fn drops_images_smaller_than_the_target() {
+        let mut selector = selector_with(0.5, &[("small.jpg", (800, 600))]);
+        let names = vec!["small.jpg".to_string()];
+
+        assert!(selector.filter_candidates(&names, (1920, 1080)).is_empty());
+    }
+
+    #[test]
+    fn drops_images_outside_the_aspect_tolerance() {
+        let mut selector = selector_with(0.01, &[("square.jpg", (2000, 2000))]);
+        let names = vec!["square.jpg".to_string()];
+
+        assert!(selector.filter_candidates(&names, (1920, 1080)).is_empty());
+    }
+
+    #[test]
+    fn zero_target_returns_every_candidate_unfiltered() {
+        let mut selector = selector_with(0.1, &[]);
+        let names = vec!["a.jpg".to_string(), "b.jpg".to_string()];
+
+        assert_eq!(selector.filter_candidates(&names, (0, 0)), names);
+    }
+
+    #[test]
+    fn uncacheable_image_is_dropped_rather_than_failing_the_whole_pool() {
+        let mut selector = selector_with(0.1, &[("good.jpg", (1920, 1080))]);
+        let names = vec!["good.jpg".to_string(), "missing.jpg".to_string()];
+
+        assert_eq!(selector.filter_candidates(&names, (1920, 1080)), vec!["good.jpg".to_string()]);
+    }
+
+    #[test]
+    fn dimensions_wraps_probe_failure_as_image_probe_error() {
+        let mut selector = ImageSelector::new(0.1);
+        match selector.dimensions("/no/such/file.jpg") {
+            Err(XFConfError::ImageProbeError(msg)) => assert!(msg.contains("/no/such/file.jpg")),
+            other => panic!("expected ImageProbeError, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! List-file parsing: a flat file of backdrop image paths, one per line,
+//! with `#` comments, `#include PATH` directives, and directory globs
+//! (`/wallpapers/**/*.jpg`) that expand to matching files on disk.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use super::XFConfError;
+
+/// Where one entry in the flattened image list came from; used so a bad
+/// `#include` or empty glob can report which file and line it appeared on.
+struct Source {
+    file: PathBuf,
+    line: usize,
+}
+
+impl Source {
+    fn describe(&self) -> String {
+        format!("{}:{}", self.file.display(), self.line)
+    }
+}
+
+/// Wrap an IO error encountered while opening `path` with the `#include`
+/// site that pulled it in, if any, so the user sees which list file
+/// referenced the missing/unreadable file instead of a bare `io::Error`.
+fn io_context(e: io::Error, path: &Path, included_from: Option<&Source>) -> XFConfError {
+    match included_from {
+        Some(src) => XFConfError::ListFileError(
+            format!("{}: could not read included file '{}': {}", src.describe(), path.display(), e)),
+        None => XFConfError::ListFileError(
+            format!("{}: could not read list file: {}", path.display(), e)),
+    }
+}
+
+/// Parse `list_path` (and anything it `#include`s) into a flattened,
+/// de-duplicated list of image file paths. Include cycles are broken by
+/// tracking canonicalized files already visited.
+pub fn read_image_names(list_path: &str) -> Result<Vec<String>, XFConfError> {
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    let mut names: Vec<String> = Vec::new();
+    read_into(Path::new(list_path), None, &mut visited, &mut names)?;
+
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+fn read_into(path: &Path, included_from: Option<&Source>, visited: &mut HashSet<PathBuf>, names: &mut Vec<String>) -> Result<(), XFConfError> {
+    let canon = fs::canonicalize(path).map_err(|e| io_context(e, path, included_from))?;
+    if !visited.insert(canon) {
+        // Already read earlier in this parse (up the include chain or via
+        // an earlier include).
+        return Ok(());
+    }
+
+    let contents = fs::read_to_string(path).map_err(|e| io_context(e, path, included_from))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    for (idx, raw_line) in contents.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw_line.trim();
+
+        if line.is_empty() || (line.starts_with('#') && !line.starts_with("#include")) {
+            continue;
+        }
+
+        if line.starts_with("#include") {
+            let included = line["#include".len()..].trim();
+            let src = Source { file: path.to_path_buf(), line: line_no };
+            if included.is_empty() {
+                return Err(XFConfError::ListFileError(
+                    format!("{}: empty #include directive", src.describe())));
+            }
+            read_into(&resolve_path(base_dir, included), Some(&src), visited, names)?;
+            continue;
+        }
+
+        if is_glob(line) {
+            let expanded = expand_glob(&resolve_path(base_dir, line))?;
+            if expanded.is_empty() {
+                let src = Source { file: path.to_path_buf(), line: line_no };
+                return Err(XFConfError::ListFileError(
+                    format!("{}: glob '{}' matched no files", src.describe(), line)));
+            }
+            for p in expanded {
+                names.push(p.to_string_lossy().into_owned());
+            }
+            continue;
+        }
+
+        names.push(resolve_path(base_dir, line).to_string_lossy().into_owned());
+    }
+
+    Ok(())
+}
+
+fn resolve_path(base_dir: &Path, entry: &str) -> PathBuf {
+    let p = Path::new(entry);
+    if p.is_absolute() {
+        p.to_path_buf()
+    } else {
+        base_dir.join(p)
+    }
+}
+
+fn is_glob(entry: &str) -> bool {
+    entry.contains('*') || entry.contains('?') || entry.contains('[')
+}
+
+/// Expand a glob pattern (supporting `*`, `?` and the recursive `**`
+/// directory wildcard) against the filesystem, one path component at a
+/// time.
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, XFConfError> {
+    let pattern_str = pattern.to_string_lossy().into_owned();
+    let components: Vec<&str> = pattern_str.split('/').filter(|c| !c.is_empty()).collect();
+
+    let mut current: Vec<PathBuf> = vec![PathBuf::from(if pattern.is_absolute() { "/" } else { "." })];
+
+    for component in components {
+        if component == "**" {
+            let mut next = Vec::new();
+            for dir in &current {
+                next.extend(walk_dirs(dir));
+            }
+            current = next;
+            continue;
+        }
+
+        let component_re = glob_component_to_regex(component)?;
+        let mut next = Vec::new();
+        for dir in &current {
+            if let Ok(entries) = fs::read_dir(dir) {
+                for entry in entries.filter_map(Result::ok) {
+                    let name = entry.file_name();
+                    if component_re.is_match(&name.to_string_lossy()) {
+                        next.push(entry.path());
+                    }
+                }
+            }
+        }
+        current = next;
+    }
+
+    current.sort();
+    current.retain(|p| p.is_file());
+    Ok(current)
+}
+
+/// All directories reachable from `root`, including `root` itself
+/// (used to implement `**`).
+fn walk_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(root) {
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.extend(walk_dirs(&path));
+            }
+        }
+    }
+    dirs
+}
+
+fn glob_component_to_regex(component: &str) -> Result<Regex, XFConfError> {
+    let mut pattern = String::from("^");
+    for c in component.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '.' | '(' | ')' | '+' | '|' | '^' | '$' | '[' | ']' | '{' | '}' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            _ => pattern.push(c),
+        }
+    }
+    pattern.push('$');
+    Ok(Regex::new(&pattern)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    /// A fresh, empty directory under the system temp dir, unique per
+    /// test invocation so parallel test runs don't collide.
+    fn tmp_dir(name: &str) -> PathBuf {
+        let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let dir = std::env::temp_dir().join(format!("xfcesetbg-listfile-test-{}-{}-{}", std::process::id(), name, id));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_file(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+        path
+    }
+
+    #[test]
+    fn flattens_sorts_and_dedups_entries() {
+        let dir = tmp_dir("flatten");
+        write_file(&dir, "a.jpg", "");
+        write_file(&dir, "b.jpg", "");
+        let list = write_file(&dir, "list.txt", "b.jpg\na.jpg\na.jpg\n");
+
+        let names = read_image_names(list.to_str().unwrap()).unwrap();
+        assert_eq!(names, vec![
+            dir.join("a.jpg").to_string_lossy().into_owned(),
+            dir.join("b.jpg").to_string_lossy().into_owned(),
+        ]);
+    }
+
+    #[test]
+    fn include_pulls_in_another_list_file() {
+        let dir = tmp_dir("include");
+        write_file(&dir, "a.jpg", "");
+        write_file(&dir, "b.jpg", "");
+        write_file(&dir, "included.txt", "b.jpg\n");
+        let list = write_file(&dir, "list.txt", "a.jpg\n#include included.txt\n");
+
+        let names = read_image_names(list.to_str().unwrap()).unwrap();
+        assert_eq!(names, vec![
+            dir.join("a.jpg").to_string_lossy().into_owned(),
+            dir.join("b.jpg").to_string_lossy().into_owned(),
+        ]);
+    }
+
+    #[test]
+    fn include_cycle_is_broken_instead_of_recursing_forever() {
+        let dir = tmp_dir("cycle");
+        write_file(&dir, "img.jpg", "");
+        write_file(&dir, "b.txt", "#include a.txt\n");
+        let a = write_file(&dir, "a.txt", "img.jpg\n#include b.txt\n");
+
+        let names = read_image_names(a.to_str().unwrap()).unwrap();
+        assert_eq!(names, vec![dir.join("img.jpg").to_string_lossy().into_owned()]);
+    }
+
+    #[test]
+    fn empty_include_directive_is_an_error() {
+        let dir = tmp_dir("empty-include");
+        let list = write_file(&dir, "list.txt", "#include\n");
+
+        match read_image_names(list.to_str().unwrap()) {
+            Err(XFConfError::ListFileError(msg)) => assert!(msg.contains("empty #include directive")),
+            other => panic!("expected ListFileError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_include_target_reports_the_including_file_and_line() {
+        let dir = tmp_dir("missing-include");
+        let list = write_file(&dir, "list.txt", "#include nope.txt\n");
+
+        match read_image_names(list.to_str().unwrap()) {
+            Err(XFConfError::ListFileError(msg)) => {
+                assert!(msg.contains("list.txt:1"), "message was: {}", msg);
+                assert!(msg.contains("nope.txt"), "message was: {}", msg);
+            }
+            other => panic!("expected ListFileError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn glob_expands_to_matching_files_in_directory() {
+        let dir = tmp_dir("glob");
+        let wallpapers = dir.join("wallpapers");
+        fs::create_dir_all(&wallpapers).unwrap();
+        write_file(&wallpapers, "one.jpg", "");
+        write_file(&wallpapers, "two.jpg", "");
+        write_file(&wallpapers, "note.txt", "");
+        let list = write_file(&dir, "list.txt", "wallpapers/*.jpg\n");
+
+        let names = read_image_names(list.to_str().unwrap()).unwrap();
+        assert_eq!(names, vec![
+            wallpapers.join("one.jpg").to_string_lossy().into_owned(),
+            wallpapers.join("two.jpg").to_string_lossy().into_owned(),
+        ]);
+    }
+}
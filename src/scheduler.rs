@@ -0,0 +1,184 @@
+//! Cooperative per-monitor rotation scheduler used by daemon mode.
+//!
+//! Each monitor gets its own timer; the daemon loop only needs to know
+//! which monitor is due soonest and how long to sleep until then.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+struct ScheduleEntry {
+    monitor: String,
+    next_fire: Instant,
+    interval: Duration,
+}
+
+impl PartialEq for ScheduleEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.next_fire == other.next_fire
+    }
+}
+
+impl Eq for ScheduleEntry {}
+
+impl PartialOrd for ScheduleEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduleEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap; reverse the comparison so the
+        // soonest `next_fire` sorts to the top.
+        other.next_fire.cmp(&self.next_fire)
+    }
+}
+
+/// Min-heap of per-monitor rotation timers, keyed by each monitor's
+/// next-fire instant.
+pub struct RotationSchedule {
+    heap: BinaryHeap<ScheduleEntry>,
+}
+
+impl RotationSchedule {
+    pub fn new() -> RotationSchedule {
+        RotationSchedule { heap: BinaryHeap::new() }
+    }
+
+    /// Schedule `monitor` to fire `interval` after `now`.
+    pub fn push(&mut self, monitor: &str, now: Instant, interval: Duration) {
+        self.heap.push(ScheduleEntry {
+            monitor: monitor.to_string(),
+            next_fire: now + interval,
+            interval: interval,
+        });
+    }
+
+    /// Drop any entry for `monitor` and push a fresh one, `interval` after `now`.
+    pub fn reschedule(&mut self, monitor: &str, now: Instant, interval: Duration) {
+        self.remove(monitor);
+        self.push(monitor, now, interval);
+    }
+
+    /// Remove every entry for `monitor` (used by `reschedule`).
+    pub fn remove(&mut self, monitor: &str) {
+        self.retain(|m| m != monitor);
+    }
+
+    /// Keep only entries whose monitor satisfies `keep`.
+    pub fn retain<F: Fn(&str) -> bool>(&mut self, keep: F) {
+        let remaining: BinaryHeap<ScheduleEntry> =
+            self.heap.drain().filter(|e| keep(&e.monitor)).collect();
+        self.heap = remaining;
+    }
+
+    pub fn contains(&self, monitor: &str) -> bool {
+        self.heap.iter().any(|e| e.monitor == monitor)
+    }
+
+    /// Instant the next entry (if any) is due to fire.
+    pub fn next_wake(&self) -> Option<Instant> {
+        self.heap.peek().map(|e| e.next_fire)
+    }
+
+    /// Pop every entry due by `now`, batching entries that fire within
+    /// `slice` of the soonest one so several monitors rotating close
+    /// together wake the daemon only once. Each popped entry is
+    /// immediately rescheduled `interval` after `now`.
+    pub fn pop_ready(&mut self, now: Instant, slice: Duration) -> Vec<String> {
+        let mut ready = Vec::new();
+
+        let cutoff = match self.heap.peek() {
+            Some(first) if first.next_fire <= now => std::cmp::max(now, first.next_fire + slice),
+            _ => return ready,
+        };
+
+        while let Some(top) = self.heap.peek() {
+            if top.next_fire > cutoff {
+                break;
+            }
+            let entry = self.heap.pop().unwrap();
+            ready.push(entry.monitor.clone());
+            self.heap.push(ScheduleEntry {
+                monitor: entry.monitor,
+                next_fire: now + entry.interval,
+                interval: entry.interval,
+            });
+        }
+
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_wake_picks_the_soonest_monitor() {
+        let now = Instant::now();
+        let mut schedule = RotationSchedule::new();
+        schedule.push("DP1", now, Duration::from_secs(10));
+        schedule.push("HDMI1", now, Duration::from_secs(5));
+
+        assert_eq!(schedule.next_wake(), Some(now + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn pop_ready_batches_entries_within_the_slice() {
+        let now = Instant::now();
+        let mut schedule = RotationSchedule::new();
+        schedule.push("DP1", now, Duration::from_millis(100));
+        schedule.push("HDMI1", now, Duration::from_millis(140));
+        schedule.push("VGA1", now, Duration::from_secs(10));
+
+        let due = schedule.pop_ready(now + Duration::from_millis(150), Duration::from_millis(50));
+        assert_eq!(due, vec!["DP1".to_string(), "HDMI1".to_string()]);
+    }
+
+    #[test]
+    fn pop_ready_reschedules_popped_entries_from_now() {
+        let now = Instant::now();
+        let mut schedule = RotationSchedule::new();
+        schedule.push("DP1", now, Duration::from_millis(100));
+
+        let fire_at = now + Duration::from_millis(100);
+        let due = schedule.pop_ready(fire_at, Duration::from_millis(50));
+        assert_eq!(due, vec!["DP1".to_string()]);
+        assert_eq!(schedule.next_wake(), Some(fire_at + Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn pop_ready_returns_nothing_before_the_soonest_entry_is_due() {
+        let now = Instant::now();
+        let mut schedule = RotationSchedule::new();
+        schedule.push("DP1", now, Duration::from_secs(10));
+
+        let due = schedule.pop_ready(now + Duration::from_secs(1), Duration::from_millis(500));
+        assert!(due.is_empty());
+    }
+
+    #[test]
+    fn remove_drops_only_the_named_monitor() {
+        let now = Instant::now();
+        let mut schedule = RotationSchedule::new();
+        schedule.push("DP1", now, Duration::from_secs(10));
+        schedule.push("HDMI1", now, Duration::from_secs(20));
+
+        schedule.remove("DP1");
+        assert!(!schedule.contains("DP1"));
+        assert!(schedule.contains("HDMI1"));
+    }
+
+    #[test]
+    fn reschedule_replaces_an_entrys_fire_time() {
+        let now = Instant::now();
+        let mut schedule = RotationSchedule::new();
+        schedule.push("DP1", now, Duration::from_secs(10));
+
+        schedule.reschedule("DP1", now, Duration::from_secs(1));
+        assert_eq!(schedule.next_wake(), Some(now + Duration::from_secs(1)));
+    }
+}